@@ -0,0 +1,548 @@
+// A small ACME (RFC 8555) client, just enough to get Discord's
+// interactions-endpoint requirement (HTTPS, valid cert) met without sitting
+// behind a reverse proxy. Only the HTTP-01 challenge type is supported,
+// since we're already serving plaintext HTTP on the same host.
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use color_eyre::{
+    eyre::{eyre, WrapErr},
+    Result,
+};
+use ring::{
+    rand::SystemRandom,
+    signature::{EcdsaKeyPair, KeyPair, ECDSA_P256_SHA256_FIXED_SIGNING},
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use tokio::sync::RwLock;
+
+const LETS_ENCRYPT_DIRECTORY_URL: &str = "https://acme-v02.api.letsencrypt.org/directory";
+// Let's Encrypt issues 90-day certificates and rate-limits identical
+// `dns:{domain}` orders to 5 per rolling 7 days, so the renewal loop must not
+// reissue on every wake-up; only once the cert is within this long of expiry.
+const CERTIFICATE_LIFETIME: Duration = Duration::from_secs(60 * 60 * 24 * 90);
+const RENEWAL_WINDOW: Duration = Duration::from_secs(60 * 60 * 24 * 30);
+
+pub struct Config {
+    pub domain: String,
+    pub contact: String,
+}
+
+impl Config {
+    /// Reads `ACME_DOMAIN`/`ACME_CONTACT`; returns `None` if either is unset,
+    /// meaning the server should fall back to plain HTTP.
+    pub fn from_env() -> Option<Self> {
+        Some(Self {
+            domain: std::env::var("ACME_DOMAIN").ok()?,
+            contact: std::env::var("ACME_CONTACT").ok()?,
+        })
+    }
+}
+
+/// Holds the in-memory challenge token this process is currently prepared to
+/// answer at `/.well-known/acme-challenge/{token}`. Shared with the axum
+/// router so the HTTP-01 challenge route can serve whatever the background
+/// ACME task is presently proving.
+#[derive(Default, Clone)]
+pub struct ChallengeResponder(std::sync::Arc<RwLock<Option<(String, String)>>>);
+
+impl ChallengeResponder {
+    async fn set(&self, token: String, key_authorization: String) {
+        *self.0.write().await = Some((token, key_authorization));
+    }
+
+    pub async fn respond(&self, token: &str) -> Option<String> {
+        let guard = self.0.read().await;
+        let (expected_token, key_authorization) = guard.as_ref()?;
+        (expected_token == token).then(|| key_authorization.clone())
+    }
+}
+
+struct Directory {
+    new_nonce: String,
+    new_account: String,
+    new_order: String,
+}
+
+#[derive(Deserialize)]
+struct DirectoryResponse {
+    #[serde(rename = "newNonce")]
+    new_nonce: String,
+    #[serde(rename = "newAccount")]
+    new_account: String,
+    #[serde(rename = "newOrder")]
+    new_order: String,
+}
+
+struct Account {
+    key_pair: EcdsaKeyPair,
+    kid: String,
+}
+
+/// Runs the ACME issuance flow once and returns a PEM-encoded (certificate
+/// chain, private key) pair. Intended to be called at startup and again by
+/// `spawn_renewal_task` as the certificate approaches expiry.
+pub async fn issue_certificate(
+    config: &Config,
+    state_dir: &Path,
+    responder: &ChallengeResponder,
+) -> Result<(String, String)> {
+    std::fs::create_dir_all(state_dir)?;
+
+    let http = reqwest::Client::new();
+    let rng = SystemRandom::new();
+
+    let directory = fetch_directory(&http).await?;
+    let account = load_or_create_account(&http, &directory, config, &rng, state_dir).await?;
+
+    let mut nonce = fetch_nonce(&http, &directory.new_nonce).await?;
+
+    let (order, order_url): (Value, String) = {
+        let payload = json!({ "identifiers": [{ "type": "dns", "value": config.domain }] });
+        let (response, location, next_nonce) =
+            signed_post_with_location(&http, &directory.new_order, &account, &nonce, &payload)
+                .await?;
+        nonce = next_nonce;
+        (response, location)
+    };
+
+    let authorization_url = order["authorizations"]
+        .as_array()
+        .and_then(|urls| urls.first())
+        .and_then(Value::as_str)
+        .ok_or_else(|| eyre!("order response had no authorizations"))?
+        .to_owned();
+
+    let (authorization, next_nonce) =
+        post_as_get(&http, &authorization_url, &account, &nonce).await?;
+    nonce = next_nonce;
+
+    let challenge = authorization["challenges"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|challenge| challenge["type"] == "http-01")
+        .ok_or_else(|| eyre!("no http-01 challenge offered"))?;
+    let challenge_url = challenge["url"]
+        .as_str()
+        .ok_or_else(|| eyre!("challenge had no url"))?
+        .to_owned();
+    let token = challenge["token"]
+        .as_str()
+        .ok_or_else(|| eyre!("challenge had no token"))?
+        .to_owned();
+
+    let key_authorization = format!("{token}.{}", jwk_thumbprint(&account.key_pair));
+    responder.set(token, key_authorization).await;
+
+    let (_, next_nonce) = signed_post(&http, &challenge_url, &account, &nonce, &json!({})).await?;
+    nonce = next_nonce;
+
+    let order = poll_until_valid(&http, &order_url, &account, &mut nonce).await?;
+
+    let (certificate_key_pem, csr_der) = generate_certificate_request(&config.domain)?;
+
+    let finalize_url = order["finalize"]
+        .as_str()
+        .ok_or_else(|| eyre!("order had no finalize url"))?
+        .to_owned();
+    let (_, next_nonce) = signed_post(
+        &http,
+        &finalize_url,
+        &account,
+        &nonce,
+        &json!({ "csr": URL_SAFE_NO_PAD.encode(csr_der) }),
+    )
+    .await?;
+    nonce = next_nonce;
+
+    let order = poll_until_valid(&http, &order_url, &account, &mut nonce).await?;
+    let certificate_url = order["certificate"]
+        .as_str()
+        .ok_or_else(|| eyre!("finalized order had no certificate url"))?;
+
+    let (_, response_bytes) =
+        signed_post_raw(&http, certificate_url, &account, &nonce, &json!({})).await?;
+    let certificate_pem = String::from_utf8(response_bytes)?;
+
+    std::fs::write(state_dir.join("cert.pem"), &certificate_pem)?;
+    std::fs::write(state_dir.join("key.pem"), &certificate_key_pem)?;
+    let issued_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    std::fs::write(state_dir.join("issued_at.txt"), issued_at.to_string())?;
+
+    Ok((certificate_pem, certificate_key_pem))
+}
+
+/// Reads back the issuance timestamp `issue_certificate` persists alongside
+/// the cert, returning `None` if it's missing or unparsable (in which case
+/// the caller should treat renewal as due, since there's nothing to trust).
+fn read_issued_at(state_dir: &Path) -> Option<SystemTime> {
+    let text = std::fs::read_to_string(state_dir.join("issued_at.txt")).ok()?;
+    let secs: u64 = text.trim().parse().ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Spawns a background task that wakes up daily and, once the certificate is
+/// within `RENEWAL_WINDOW` of the end of its `CERTIFICATE_LIFETIME`, re-runs
+/// `issue_certificate` and hands the renewed PEM pair to `on_renewed`.
+pub fn spawn_renewal_task(
+    config: Config,
+    state_dir: PathBuf,
+    responder: ChallengeResponder,
+    on_renewed: impl Fn(String, String) + Send + 'static,
+) {
+    tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(Duration::from_secs(60 * 60 * 24)).await;
+
+            let renewal_due = match read_issued_at(&state_dir) {
+                Some(issued_at) => {
+                    issued_at.elapsed().unwrap_or(Duration::MAX)
+                        >= CERTIFICATE_LIFETIME - RENEWAL_WINDOW
+                }
+                None => true,
+            };
+            if !renewal_due {
+                continue;
+            }
+
+            match issue_certificate(&config, &state_dir, &responder).await {
+                Ok((cert, key)) => on_renewed(cert, key),
+                Err(error) => {
+                    eprintln!("certificate renewal failed, will retry tomorrow: {error:?}")
+                }
+            }
+        }
+    });
+}
+
+async fn fetch_directory(http: &reqwest::Client) -> Result<Directory> {
+    let response: DirectoryResponse = http
+        .get(LETS_ENCRYPT_DIRECTORY_URL)
+        .send()
+        .await?
+        .json()
+        .await?;
+    Ok(Directory {
+        new_nonce: response.new_nonce,
+        new_account: response.new_account,
+        new_order: response.new_order,
+    })
+}
+
+async fn fetch_nonce(http: &reqwest::Client, new_nonce_url: &str) -> Result<String> {
+    let response = http.head(new_nonce_url).send().await?;
+    replay_nonce(&response)
+}
+
+fn replay_nonce(response: &reqwest::Response) -> Result<String> {
+    response
+        .headers()
+        .get("Replay-Nonce")
+        .ok_or_else(|| eyre!("response carried no Replay-Nonce header"))?
+        .to_str()
+        .map(str::to_owned)
+        .wrap_err("Replay-Nonce header was not valid ascii")
+}
+
+async fn load_or_create_account(
+    http: &reqwest::Client,
+    directory: &Directory,
+    config: &Config,
+    rng: &SystemRandom,
+    state_dir: &Path,
+) -> Result<Account> {
+    let key_path = state_dir.join("account_key.der");
+    let kid_path = state_dir.join("account_kid.txt");
+
+    let pkcs8 = if let Ok(bytes) = std::fs::read(&key_path) {
+        bytes
+    } else {
+        let generated = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, rng)
+            .map_err(|_| eyre!("failed to generate account key"))?
+            .as_ref()
+            .to_vec();
+        std::fs::write(&key_path, &generated)?;
+        generated
+    };
+    let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &pkcs8, rng)
+        .map_err(|_| eyre!("failed to load account key"))?;
+
+    if let Ok(kid) = std::fs::read_to_string(&kid_path) {
+        return Ok(Account { key_pair, kid });
+    }
+
+    let nonce = fetch_nonce(http, &directory.new_nonce).await?;
+    let payload = json!({
+        "termsOfServiceAgreed": true,
+        "contact": [format!("mailto:{}", config.contact)],
+    });
+    let protected = json!({
+        "alg": "ES256",
+        "jwk": jwk(&key_pair),
+        "nonce": nonce,
+        "url": directory.new_account,
+    });
+    let body = jws(&key_pair, &protected, &payload)?;
+    let response = http
+        .post(&directory.new_account)
+        .header("Content-Type", "application/jose+json")
+        .body(body)
+        .send()
+        .await?;
+    let kid = response
+        .headers()
+        .get("Location")
+        .ok_or_else(|| eyre!("account creation response carried no Location header"))?
+        .to_str()?
+        .to_owned();
+    std::fs::write(&kid_path, &kid)?;
+
+    Ok(Account { key_pair, kid })
+}
+
+async fn signed_post(
+    http: &reqwest::Client,
+    url: &str,
+    account: &Account,
+    nonce: &str,
+    payload: &Value,
+) -> Result<(Value, String)> {
+    let (bytes, _location, next_nonce) =
+        signed_post_with_nonce(http, url, account, nonce, payload).await?;
+    Ok((serde_json::from_slice(&bytes)?, next_nonce))
+}
+
+/// Like `signed_post`, but also returns the response's `Location` header —
+/// that's where `newOrder` hands back the order's own URL, which is needed
+/// to poll and finalize it later and is not present anywhere in its body.
+async fn signed_post_with_location(
+    http: &reqwest::Client,
+    url: &str,
+    account: &Account,
+    nonce: &str,
+    payload: &Value,
+) -> Result<(Value, String, String)> {
+    let (bytes, location, next_nonce) =
+        signed_post_with_nonce(http, url, account, nonce, payload).await?;
+    let location = location.ok_or_else(|| eyre!("response carried no Location header"))?;
+    Ok((serde_json::from_slice(&bytes)?, location, next_nonce))
+}
+
+async fn signed_post_raw(
+    http: &reqwest::Client,
+    url: &str,
+    account: &Account,
+    nonce: &str,
+    payload: &Value,
+) -> Result<(String, Vec<u8>)> {
+    let (bytes, _location, next_nonce) =
+        signed_post_with_nonce(http, url, account, nonce, payload).await?;
+    Ok((next_nonce, bytes))
+}
+
+async fn signed_post_with_nonce(
+    http: &reqwest::Client,
+    url: &str,
+    account: &Account,
+    nonce: &str,
+    payload: &Value,
+) -> Result<(Vec<u8>, Option<String>, String)> {
+    let protected = json!({
+        "alg": "ES256",
+        "kid": account.kid,
+        "nonce": nonce,
+        "url": url,
+    });
+    let body = jws(&account.key_pair, &protected, payload)?;
+    let response = http
+        .post(url)
+        .header("Content-Type", "application/jose+json")
+        .body(body)
+        .send()
+        .await?;
+    let next_nonce = replay_nonce(&response)?;
+    let location = response
+        .headers()
+        .get("Location")
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned);
+    if !response.status().is_success() {
+        return Err(eyre!(response.text().await?));
+    }
+    let bytes = response.bytes().await?.to_vec();
+    Ok((bytes, location, next_nonce))
+}
+
+async fn post_as_get(
+    http: &reqwest::Client,
+    url: &str,
+    account: &Account,
+    nonce: &str,
+) -> Result<(Value, String)> {
+    let protected = json!({
+        "alg": "ES256",
+        "kid": account.kid,
+        "nonce": nonce,
+        "url": url,
+    });
+    let body = jws_with_empty_payload(&account.key_pair, &protected)?;
+    let response = http
+        .post(url)
+        .header("Content-Type", "application/jose+json")
+        .body(body)
+        .send()
+        .await?;
+    let next_nonce = replay_nonce(&response)?;
+    let value = response.json().await?;
+    Ok((value, next_nonce))
+}
+
+async fn poll_until_valid(
+    http: &reqwest::Client,
+    order_url: &str,
+    account: &Account,
+    nonce: &mut String,
+) -> Result<Value> {
+    for _ in 0..20 {
+        let (order, next_nonce) = post_as_get(http, order_url, account, nonce).await?;
+        *nonce = next_nonce;
+        match order["status"].as_str() {
+            Some("valid") | Some("ready") => return Ok(order),
+            Some("invalid") => return Err(eyre!("order became invalid: {order}")),
+            _ => tokio::time::sleep(Duration::from_secs(2)).await,
+        }
+    }
+    Err(eyre!("timed out waiting for order to become valid"))
+}
+
+/// Builds a flattened JWS per RFC 7515: base64url(protected header) + "." +
+/// base64url(payload), signed and wrapped into the JSON body ACME expects.
+fn jws(key_pair: &EcdsaKeyPair, protected: &Value, payload: &Value) -> Result<String> {
+    jws_inner(key_pair, protected, Some(payload))
+}
+
+fn jws_with_empty_payload(key_pair: &EcdsaKeyPair, protected: &Value) -> Result<String> {
+    jws_inner(key_pair, protected, None)
+}
+
+fn jws_inner(
+    key_pair: &EcdsaKeyPair,
+    protected: &Value,
+    payload: Option<&Value>,
+) -> Result<String> {
+    let protected_b64 = URL_SAFE_NO_PAD.encode(serde_json::to_vec(protected)?);
+    let payload_b64 = match payload {
+        Some(payload) => URL_SAFE_NO_PAD.encode(serde_json::to_vec(payload)?),
+        None => String::new(),
+    };
+    let signing_input = format!("{protected_b64}.{payload_b64}");
+    let signature = key_pair
+        .sign(&SystemRandom::new(), signing_input.as_bytes())
+        .map_err(|_| eyre!("failed to sign ACME request"))?;
+    let signature_b64 = URL_SAFE_NO_PAD.encode(signature.as_ref());
+
+    Ok(serde_json::to_string(&json!({
+        "protected": protected_b64,
+        "payload": payload_b64,
+        "signature": signature_b64,
+    }))?)
+}
+
+fn jwk(key_pair: &EcdsaKeyPair) -> Value {
+    let point = key_pair.public_key().as_ref();
+    // Uncompressed SEC1 point: 0x04 || x (32 bytes) || y (32 bytes).
+    let (x, y) = (&point[1..33], &point[33..65]);
+    json!({
+        "crv": "P-256",
+        "kty": "EC",
+        "x": URL_SAFE_NO_PAD.encode(x),
+        "y": URL_SAFE_NO_PAD.encode(y),
+    })
+}
+
+fn jwk_thumbprint(key_pair: &EcdsaKeyPair) -> String {
+    // RFC 7638: SHA-256 over the JWK members in lexicographic key order.
+    let jwk = jwk(key_pair);
+    let canonical = format!(
+        r#"{{"crv":"P-256","kty":"EC","x":"{}","y":"{}"}}"#,
+        jwk["x"].as_str().unwrap(),
+        jwk["y"].as_str().unwrap(),
+    );
+    let digest = ring::digest::digest(&ring::digest::SHA256, canonical.as_bytes());
+    URL_SAFE_NO_PAD.encode(digest.as_ref())
+}
+
+/// Generates a fresh ECDSA P-256 certificate key and a DER-encoded CSR for
+/// `domain`, returning the key as PEM alongside the CSR.
+fn generate_certificate_request(domain: &str) -> Result<(String, Vec<u8>)> {
+    let mut params = rcgen::CertificateParams::new(vec![domain.to_owned()]);
+    params.alg = &rcgen::PKCS_ECDSA_P256_SHA256;
+    let certificate = rcgen::Certificate::from_params(params)?;
+    let csr_der = certificate.serialize_request_der()?;
+    let key_pem = certificate.serialize_private_key_pem();
+    Ok((key_pem, csr_der))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_key_pair() -> EcdsaKeyPair {
+        let rng = SystemRandom::new();
+        let pkcs8 = EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, &rng).unwrap();
+        EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_FIXED_SIGNING, pkcs8.as_ref(), &rng).unwrap()
+    }
+
+    #[test]
+    fn jwk_thumbprint_is_deterministic_for_the_same_key() {
+        let key_pair = test_key_pair();
+        assert_eq!(jwk_thumbprint(&key_pair), jwk_thumbprint(&key_pair));
+    }
+
+    #[test]
+    fn jwk_thumbprint_differs_across_distinct_keys() {
+        assert_ne!(
+            jwk_thumbprint(&test_key_pair()),
+            jwk_thumbprint(&test_key_pair())
+        );
+    }
+
+    #[test]
+    fn jws_signature_verifies_against_the_signing_key() {
+        let key_pair = test_key_pair();
+        let protected = json!({"alg": "ES256", "nonce": "abc", "url": "https://example.com"});
+        let payload = json!({"hello": "world"});
+
+        let body = jws(&key_pair, &protected, &payload).unwrap();
+        let parsed: Value = serde_json::from_str(&body).unwrap();
+        let protected_b64 = parsed["protected"].as_str().unwrap();
+        let payload_b64 = parsed["payload"].as_str().unwrap();
+        let signature = URL_SAFE_NO_PAD
+            .decode(parsed["signature"].as_str().unwrap())
+            .unwrap();
+        let signing_input = format!("{protected_b64}.{payload_b64}");
+
+        let public_key = ring::signature::UnparsedPublicKey::new(
+            &ring::signature::ECDSA_P256_SHA256_FIXED,
+            key_pair.public_key().as_ref(),
+        );
+        assert!(public_key
+            .verify(signing_input.as_bytes(), &signature)
+            .is_ok());
+    }
+
+    #[test]
+    fn jws_with_empty_payload_signs_an_empty_payload_segment() {
+        let key_pair = test_key_pair();
+        let protected = json!({"alg": "ES256", "nonce": "abc", "url": "https://example.com"});
+
+        let body = jws_with_empty_payload(&key_pair, &protected).unwrap();
+        let parsed: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(parsed["payload"].as_str().unwrap(), "");
+    }
+}
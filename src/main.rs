@@ -1,9 +1,9 @@
-use api::Command;
+use api::{Command, CommandOption, CommandOptionChoice, CommandOptionType};
 use axum::{
     body::Bytes,
-    extract::State,
+    extract::{Path, State},
     http::{HeaderMap, StatusCode},
-    routing::post,
+    routing::{get, post},
     Json, Router,
 };
 use color_eyre::{
@@ -15,6 +15,9 @@ use rand::seq::SliceRandom;
 use serde::{Deserialize, Serialize};
 use tokio::net::TcpListener;
 
+mod acme;
+mod ratelimit;
+
 mod api {
     use color_eyre::{eyre::eyre, Result};
     use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION};
@@ -22,8 +25,14 @@ mod api {
 
     const DISCORD_URL: &str = "https://discord.com/api/v10";
 
+    #[derive(Clone)]
     pub struct Client {
         inner: reqwest::Client,
+        // Webhook follow-ups (editing a deferred interaction's original
+        // response) are authenticated by the interaction token in the URL,
+        // not the bot token, so they go out through a client with no
+        // Authorization header at all.
+        unauthenticated: reqwest::Client,
     }
 
     impl Client {
@@ -37,7 +46,10 @@ mod api {
                 .default_headers(header_map)
                 .build()?;
 
-            Ok(Self { inner })
+            Ok(Self {
+                inner,
+                unauthenticated: reqwest::Client::new(),
+            })
         }
 
         pub async fn get_application(&self) -> Result<Application> {
@@ -76,6 +88,28 @@ mod api {
             }
         }
 
+        pub async fn edit_original_response(
+            &self,
+            application_id: &str,
+            interaction_token: &str,
+            message: &impl Serialize,
+        ) -> Result<()> {
+            let response = self
+                .unauthenticated
+                .patch(format!(
+                    "{DISCORD_URL}/webhooks/{application_id}/{interaction_token}/messages/@original"
+                ))
+                .json(message)
+                .send()
+                .await?;
+
+            if !response.status().is_success() {
+                Err(eyre!(response.text().await?))
+            } else {
+                Ok(())
+            }
+        }
+
         pub async fn set_interaction_endpoints_url(&self, url: &str) -> Result<()> {
             let response = self
                 .inner
@@ -98,6 +132,46 @@ mod api {
     pub struct Command {
         pub name: &'static str,
         pub description: &'static str,
+        #[serde(skip_serializing_if = "<[_]>::is_empty")]
+        pub options: &'static [CommandOption],
+    }
+
+    #[derive(Serialize)]
+    pub struct CommandOption {
+        #[serde(rename = "type")]
+        pub option_type: CommandOptionType,
+        pub name: &'static str,
+        pub description: &'static str,
+        #[serde(skip_serializing_if = "std::ops::Not::not")]
+        pub required: bool,
+        #[serde(skip_serializing_if = "<[_]>::is_empty")]
+        pub choices: &'static [CommandOptionChoice],
+    }
+
+    #[derive(Serialize)]
+    pub struct CommandOptionChoice {
+        pub name: &'static str,
+        pub value: &'static str,
+    }
+
+    // https://discord.com/developers/docs/interactions/application-commands#application-command-object-application-command-option-type
+    pub enum CommandOptionType {
+        String,
+        Integer,
+        Boolean,
+    }
+
+    impl Serialize for CommandOptionType {
+        fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+        where
+            S: serde::Serializer,
+        {
+            serializer.serialize_u8(match self {
+                CommandOptionType::String => 3,
+                CommandOptionType::Integer => 4,
+                CommandOptionType::Boolean => 5,
+            })
+        }
     }
 
     #[derive(Deserialize)]
@@ -107,6 +181,146 @@ mod api {
     }
 }
 
+// `city.txt` lines are `name|country|continent|population`; parsed once and
+// kept around so per-request filtering (by min population, by continent) is
+// just a slice scan rather than a re-parse of the whole file.
+mod city {
+    use std::sync::OnceLock;
+
+    use rand::Rng;
+
+    pub struct Record {
+        pub name: &'static str,
+        pub country: &'static str,
+        pub continent: &'static str,
+        pub population: u64,
+    }
+
+    fn parse_line(line: &'static str) -> Option<Record> {
+        let mut fields = line.split('|');
+        Some(Record {
+            name: fields.next()?,
+            country: fields.next()?,
+            continent: fields.next()?,
+            population: fields.next()?.parse().ok()?,
+        })
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn parse_line_reads_all_fields() {
+            let record = parse_line("Tokyo|Japan|Asia|37400000").unwrap();
+            assert_eq!(record.name, "Tokyo");
+            assert_eq!(record.country, "Japan");
+            assert_eq!(record.continent, "Asia");
+            assert_eq!(record.population, 37_400_000);
+        }
+
+        #[test]
+        fn parse_line_rejects_missing_fields() {
+            assert!(parse_line("Tokyo|Japan|Asia").is_none());
+        }
+
+        #[test]
+        fn parse_line_rejects_non_numeric_population() {
+            assert!(parse_line("Tokyo|Japan|Asia|not-a-number").is_none());
+        }
+
+        fn weighted(text: &'static str) -> WeightedRecords {
+            WeightedRecords::new(text)
+        }
+
+        #[test]
+        fn cumulative_weight_is_a_prefix_sum_with_zero_population_floored_to_one() {
+            // A|10, B|0 (floored to 1), C|5
+            let records = weighted("A|X|Asia|10\nB|Y|Europe|0\nC|Z|Africa|5\n");
+            assert_eq!(records.cumulative_weight, vec![10, 11, 16]);
+            assert_eq!(records.total_weight, 16);
+        }
+
+        #[test]
+        fn partition_point_resolves_targets_landing_exactly_on_a_boundary() {
+            // Same table as above: A occupies [0, 10), B occupies [10, 11),
+            // C occupies [11, 16) of the weight space.
+            let records = weighted("A|X|Asia|10\nB|Y|Europe|0\nC|Z|Africa|5\n");
+            let index_for = |target| records.cumulative_weight.partition_point(|&w| w <= target);
+
+            assert_eq!(index_for(0), 0);
+            assert_eq!(index_for(9), 0); // last unit still in A's bucket
+            assert_eq!(index_for(10), 1); // first unit of B's bucket
+            assert_eq!(index_for(11), 2); // first unit of C's bucket
+            assert_eq!(index_for(15), 2); // last unit, still in C's bucket
+        }
+    }
+
+    /// A parsed-once table plus a cumulative-weight prefix sum over
+    /// `population` (zero population treated as weight 1; lines whose
+    /// population is missing or not a number are dropped by `parse_line`
+    /// and never drawn), so an
+    /// unfiltered population-weighted pick is a single `gen_range` and a
+    /// binary search rather than an O(n) rescan on every request.
+    pub struct WeightedRecords {
+        records: Vec<Record>,
+        // cumulative_weight[i] == sum of weight(records[0..=i])
+        cumulative_weight: Vec<u64>,
+        total_weight: u64,
+    }
+
+    impl WeightedRecords {
+        fn new(text: &'static str) -> Self {
+            let records: Vec<_> = text.lines().filter_map(parse_line).collect();
+
+            let mut total_weight = 0u64;
+            let cumulative_weight = records
+                .iter()
+                .map(|record| {
+                    total_weight += record.population.max(1);
+                    total_weight
+                })
+                .collect();
+
+            Self {
+                records,
+                cumulative_weight,
+                total_weight,
+            }
+        }
+
+        pub fn all(&self) -> &[Record] {
+            &self.records
+        }
+
+        pub fn random_uniform(&self, rng: &mut impl Rng) -> Option<&Record> {
+            use rand::seq::SliceRandom;
+            self.records.choose(rng)
+        }
+
+        pub fn random_weighted(&self, rng: &mut impl Rng) -> Option<&Record> {
+            if self.total_weight == 0 {
+                return None;
+            }
+            let target = rng.gen_range(0..self.total_weight);
+            let index = self
+                .cumulative_weight
+                .partition_point(|&weight| weight <= target);
+            self.records.get(index)
+        }
+    }
+
+    pub fn records() -> &'static WeightedRecords {
+        static RECORDS: OnceLock<WeightedRecords> = OnceLock::new();
+        RECORDS.get_or_init(|| WeightedRecords::new(include_str!("city.txt")))
+    }
+
+    pub fn usa_records() -> &'static WeightedRecords {
+        static RECORDS: OnceLock<WeightedRecords> = OnceLock::new();
+        RECORDS.get_or_init(|| WeightedRecords::new(include_str!("usacity.txt")))
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     let bot_key = std::env::var("BOT_KEY").wrap_err(eyre!("must specify bot key with BOT_KEY"))?;
@@ -124,15 +338,77 @@ async fn main() -> Result<()> {
                 Command {
                     name: "city",
                     description: "generate a random city (population min: 100,000)",
+                    options: &[
+                        CommandOption {
+                            option_type: CommandOptionType::Integer,
+                            name: "min_population",
+                            description: "only generate cities with at least this population",
+                            required: false,
+                            choices: &[],
+                        },
+                        CommandOption {
+                            option_type: CommandOptionType::String,
+                            name: "continent",
+                            description: "only generate cities on this continent",
+                            required: false,
+                            choices: &[
+                                CommandOptionChoice {
+                                    name: "Africa",
+                                    value: "Africa",
+                                },
+                                CommandOptionChoice {
+                                    name: "Antarctica",
+                                    value: "Antarctica",
+                                },
+                                CommandOptionChoice {
+                                    name: "Asia",
+                                    value: "Asia",
+                                },
+                                CommandOptionChoice {
+                                    name: "Europe",
+                                    value: "Europe",
+                                },
+                                CommandOptionChoice {
+                                    name: "North America",
+                                    value: "North America",
+                                },
+                                CommandOptionChoice {
+                                    name: "Oceania",
+                                    value: "Oceania",
+                                },
+                                CommandOptionChoice {
+                                    name: "South America",
+                                    value: "South America",
+                                },
+                            ],
+                        },
+                        CommandOption {
+                            option_type: CommandOptionType::Boolean,
+                            name: "weighted",
+                            description:
+                                "weight by population instead of picking uniformly (default: true)",
+                            required: false,
+                            choices: &[],
+                        },
+                    ],
                 },
                 Command {
                     name: "usacity",
                     description:
                         "generate a random city that is in the USA (population min: 100,000)",
+                    options: &[CommandOption {
+                        option_type: CommandOptionType::Boolean,
+                        name: "weighted",
+                        description:
+                            "weight by population instead of picking uniformly (default: true)",
+                        required: false,
+                        choices: &[],
+                    }],
                 },
                 Command {
                     name: "state",
                     description: "generate a random state",
+                    options: &[],
                 },
             ],
             app.id,
@@ -140,8 +416,9 @@ async fn main() -> Result<()> {
         .await?;
 
     // spawned in a task because this call needs the server to be running in order to eventually succeed
+    let endpoint_client = discord_client.clone();
     tokio::spawn(async move {
-        discord_client
+        endpoint_client
             .set_interaction_endpoints_url(&interactions_endpoint_url)
             .await
             .unwrap()
@@ -152,17 +429,91 @@ async fn main() -> Result<()> {
         VerifyingKey::from_bytes(&bytes)?
     };
 
-    let app = Router::new()
-        .route("/", post(handle))
-        .with_state(verifying_key);
-    let listener = TcpListener::bind("0.0.0.0:3000").await?;
-    axum::serve(listener, app).await?;
+    let limiter = match std::env::var("REDIS_URL") {
+        Ok(redis_url) => Some(ratelimit::Limiter::connect(&redis_url).await?),
+        Err(_) => None,
+    };
+
+    let router = Router::new().route("/", post(handle)).with_state(AppState {
+        verifying_key,
+        discord_client,
+        limiter,
+    });
+
+    match acme::Config::from_env() {
+        Some(acme_config) => {
+            // The HTTP-01 challenge is always validated over plaintext port
+            // 80, so that listener has to be up (and stay up, for later
+            // renewals) before we ever ask Let's Encrypt to start checking it.
+            let challenge_responder = acme::ChallengeResponder::default();
+            let challenge_router = Router::new()
+                .route(
+                    "/.well-known/acme-challenge/:token",
+                    get(serve_acme_challenge),
+                )
+                .with_state(challenge_responder.clone());
+            tokio::spawn(async move {
+                let listener = TcpListener::bind("0.0.0.0:80").await.unwrap();
+                axum::serve(listener, challenge_router).await.unwrap()
+            });
+
+            let state_dir = std::path::PathBuf::from("acme-state");
+            let (cert_pem, key_pem) =
+                acme::issue_certificate(&acme_config, &state_dir, &challenge_responder).await?;
+            let tls_config = axum_server::tls_rustls::RustlsConfig::from_pem(
+                cert_pem.into_bytes(),
+                key_pem.into_bytes(),
+            )
+            .await?;
+
+            acme::spawn_renewal_task(acme_config, state_dir, challenge_responder, {
+                let tls_config = tls_config.clone();
+                move |cert_pem, key_pem| {
+                    let tls_config = tls_config.clone();
+                    tokio::spawn(async move {
+                        if let Err(error) = tls_config
+                            .reload_from_pem(cert_pem.into_bytes(), key_pem.into_bytes())
+                            .await
+                        {
+                            eprintln!("failed to reload renewed certificate: {error:?}");
+                        }
+                    });
+                }
+            });
+
+            axum_server::bind_rustls("0.0.0.0:443".parse()?, tls_config)
+                .serve(router.into_make_service())
+                .await?;
+        }
+        None => {
+            let listener = TcpListener::bind("0.0.0.0:3000").await?;
+            axum::serve(listener, router).await?;
+        }
+    }
+
     Ok(())
 }
 
+#[derive(Clone)]
+struct AppState {
+    verifying_key: VerifyingKey,
+    discord_client: api::Client,
+    limiter: Option<ratelimit::Limiter>,
+}
+
+async fn serve_acme_challenge(
+    State(challenge_responder): State<acme::ChallengeResponder>,
+    Path(token): Path<String>,
+) -> Result<String, StatusCode> {
+    challenge_responder
+        .respond(&token)
+        .await
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
 #[axum::debug_handler]
 async fn handle(
-    State(verifying_key): State<VerifyingKey>,
+    State(state): State<AppState>,
     headers: HeaderMap,
     body: Bytes,
 ) -> Result<Json<InteractionResponse>, (StatusCode, &'static str)> {
@@ -187,50 +538,234 @@ async fn handle(
             )
         })?;
 
-    verify_discord_message(verifying_key, signature, timestamp, &body)
+    verify_discord_message(state.verifying_key, signature, timestamp, &body)
         .map_err(|_| (StatusCode::UNAUTHORIZED, "invalid!!!"))?;
 
+    if let Some(limiter) = &state.limiter {
+        limiter
+            .check_timestamp(timestamp)
+            .map_err(|_| (StatusCode::UNAUTHORIZED, "stale request"))?;
+
+        let is_replay = limiter
+            .is_replay(signature)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "rate limit check failed"))?;
+        if is_replay {
+            return Err((StatusCode::UNAUTHORIZED, "replayed request"));
+        }
+    }
+
     let interaction: Interaction = serde_json::from_slice(&body)
         .map_err(|_| (StatusCode::BAD_REQUEST, "failed to parse interaction"))?;
 
+    if let (Some(limiter), Some(user_id)) = (&state.limiter, interaction.user_id()) {
+        let within_quota = limiter
+            .check_quota(user_id)
+            .await
+            .map_err(|_| (StatusCode::INTERNAL_SERVER_ERROR, "rate limit check failed"))?;
+        if !within_quota {
+            return Err((StatusCode::TOO_MANY_REQUESTS, "command quota exceeded"));
+        }
+    }
+
     let response = match interaction {
         Interaction::Ping { .. } => InteractionResponse::Pong {
             _type: InteractionCallbackType,
         },
-        Interaction::ApplicationCommand { data, .. } => match data.name.as_str() {
-            "city" => InteractionResponse::ChannelMessageWithSource {
-                _type: InteractionCallbackType,
-                data: Message {
-                    content: {
-                        let cities: Vec<_> = include_str!("city.txt").lines().collect();
-                        (*cities.choose(&mut rand::thread_rng()).unwrap()).to_owned()
-                    },
-                },
-            },
+        Interaction::ApplicationCommand {
+            data,
+            application_id,
+            token,
+            ..
+        } => match data.name.as_str() {
+            // `city` replies via the deferred flow: ack immediately, then
+            // generate the pick and PATCH it in once it's ready. Real reason
+            // to defer here would be an external geocoding/population
+            // lookup; this exercises the same plumbing without one.
+            "city" => {
+                let min_population = data.option_u64("min_population").unwrap_or(0);
+                let continent = data.option_str("continent").map(str::to_owned);
+                let weighted = data.option_bool("weighted").unwrap_or(true);
+                let reroll_id = city_custom_id(min_population, continent.as_deref(), weighted);
+                let discord_client = state.discord_client.clone();
+
+                tokio::spawn(async move {
+                    // The deferred ack and this follow-up PATCH travel over
+                    // independent connections, so nothing guarantees Discord
+                    // has registered the ack before the edit lands — racing
+                    // ahead 404s with "interaction has not been responded".
+                    // There's no real latency here to justify deferring in
+                    // the first place, so just give the ack a head start.
+                    tokio::time::sleep(std::time::Duration::from_millis(750)).await;
+
+                    let message = Message {
+                        content: random_city(min_population, continent.as_deref(), weighted),
+                        components: Some(reroll_button(&reroll_id)),
+                    };
+                    if let Err(error) = discord_client
+                        .edit_original_response(&application_id, &token, &message)
+                        .await
+                    {
+                        eprintln!("failed to deliver deferred city response: {error:?}");
+                    }
+                });
+
+                InteractionResponse::DeferredChannelMessageWithSource {
+                    _type: InteractionCallbackType,
+                }
+            }
             "usacity" => InteractionResponse::ChannelMessageWithSource {
                 _type: InteractionCallbackType,
                 data: Message {
-                    content: {
-                        let usa_cities: Vec<_> = include_str!("usacity.txt").lines().collect();
-                        (*usa_cities.choose(&mut rand::thread_rng()).unwrap()).to_owned()
-                    },
+                    content: random_usacity(data.option_bool("weighted").unwrap_or(true)),
+                    components: Some(reroll_button("usacity")),
                 },
             },
             "state" => InteractionResponse::ChannelMessageWithSource {
                 _type: InteractionCallbackType,
                 data: Message {
-                    content: {
-                        let states: Vec<_> = include_str!("state.txt").lines().collect();
-                        (*states.choose(&mut rand::thread_rng()).unwrap()).to_owned()
-                    },
+                    content: random_line(include_str!("state.txt")),
+                    components: Some(reroll_button("state")),
                 },
             },
             _ => return Err((StatusCode::BAD_REQUEST, "unknown command")),
         },
+        Interaction::MessageComponent { data, .. } => {
+            let command = data
+                .custom_id
+                .strip_prefix("reroll:")
+                .ok_or((StatusCode::BAD_REQUEST, "unknown component"))?;
+
+            let message = if let Some(filters) = command.strip_prefix("city:") {
+                // Carries the original command's filters forward so "Reroll"
+                // draws from the same filtered population instead of
+                // silently reverting to an unfiltered pick.
+                let (min_population, continent, weighted) = parse_city_custom_id(filters);
+                Message {
+                    content: random_city(min_population, continent.as_deref(), weighted),
+                    components: Some(reroll_button(&city_custom_id(
+                        min_population,
+                        continent.as_deref(),
+                        weighted,
+                    ))),
+                }
+            } else {
+                match command {
+                    "usacity" => Message {
+                        content: random_usacity(true),
+                        components: Some(reroll_button("usacity")),
+                    },
+                    "state" => Message {
+                        content: random_line(include_str!("state.txt")),
+                        components: Some(reroll_button("state")),
+                    },
+                    _ => return Err((StatusCode::BAD_REQUEST, "unknown reroll target")),
+                }
+            };
+
+            InteractionResponse::UpdateMessage {
+                _type: InteractionCallbackType,
+                data: message,
+            }
+        }
     };
     Ok(Json(response))
 }
 
+fn random_city(min_population: u64, continent: Option<&str>, weighted: bool) -> String {
+    let records = city::records();
+
+    // Fast path: no filters means we can draw straight from the
+    // precomputed, whole-dataset cumulative-weight index in O(log n)
+    // instead of re-collecting a filtered copy of the table.
+    if min_population == 0 && continent.is_none() {
+        let chosen = if weighted {
+            records.random_weighted(&mut rand::thread_rng())
+        } else {
+            records.random_uniform(&mut rand::thread_rng())
+        };
+        return format_city(chosen);
+    }
+
+    let matching: Vec<_> = records
+        .all()
+        .iter()
+        .filter(|city| city.population >= min_population)
+        .filter(|city| continent.map_or(true, |wanted| city.continent == wanted))
+        .collect();
+
+    let chosen = if weighted {
+        matching
+            .choose_weighted(&mut rand::thread_rng(), |city| city.population.max(1))
+            .ok()
+            .copied()
+    } else {
+        matching.choose(&mut rand::thread_rng()).copied()
+    };
+
+    format_city(chosen)
+}
+
+/// Encodes `/city`'s filters into a `reroll:city:...` custom_id so a later
+/// "Reroll" press can reapply the same filters instead of drawing from the
+/// whole, unfiltered table.
+fn city_custom_id(min_population: u64, continent: Option<&str>, weighted: bool) -> String {
+    format!(
+        "city:{min_population}:{}:{}",
+        continent.unwrap_or(""),
+        weighted as u8
+    )
+}
+
+/// Inverse of `city_custom_id`; given the part of the custom_id after
+/// `"city:"`, recovers the original filters (defaulting to "no filter" on
+/// anything malformed rather than failing the reroll).
+fn parse_city_custom_id(payload: &str) -> (u64, Option<String>, bool) {
+    let mut fields = payload.splitn(3, ':');
+    let min_population = fields
+        .next()
+        .and_then(|field| field.parse().ok())
+        .unwrap_or(0);
+    let continent = fields
+        .next()
+        .filter(|field| !field.is_empty())
+        .map(str::to_owned);
+    let weighted = fields.next().map_or(true, |field| field != "0");
+    (min_population, continent, weighted)
+}
+
+fn random_usacity(weighted: bool) -> String {
+    let records = city::usa_records();
+    let chosen = if weighted {
+        records.random_weighted(&mut rand::thread_rng())
+    } else {
+        records.random_uniform(&mut rand::thread_rng())
+    };
+    format_city(chosen)
+}
+
+fn format_city(city: Option<&city::Record>) -> String {
+    city.map(|city| format!("{}, {}", city.name, city.country))
+        .unwrap_or_else(|| "no city matches those filters".to_owned())
+}
+
+fn random_line(text: &str) -> String {
+    let lines: Vec<_> = text.lines().collect();
+    (*lines.choose(&mut rand::thread_rng()).unwrap()).to_owned()
+}
+
+fn reroll_button(command: &str) -> Vec<ActionRow> {
+    vec![ActionRow {
+        _type: ComponentType,
+        components: vec![Button {
+            _type: ComponentType,
+            style: ButtonStyle::Secondary,
+            label: "Reroll",
+            custom_id: format!("reroll:{command}"),
+        }],
+    }]
+}
+
 pub fn verify_discord_message(
     public_key: VerifyingKey,
     signature: &str,
@@ -271,12 +806,146 @@ enum Interaction {
         #[serde(rename = "type")]
         _type: InteractionType<2>,
         data: ApplicationCommandData,
+        application_id: String,
+        token: String,
+        #[serde(flatten)]
+        interactor: Interactor,
     },
+    MessageComponent {
+        #[serde(rename = "type")]
+        _type: InteractionType<3>,
+        data: MessageComponentData,
+        #[serde(flatten)]
+        interactor: Interactor,
+    },
+}
+
+impl Interaction {
+    /// The id of the user who triggered this interaction, used as the rate
+    /// limit key. Present on every variant but `Ping`.
+    fn user_id(&self) -> Option<&str> {
+        match self {
+            Interaction::Ping { .. } => None,
+            Interaction::ApplicationCommand { interactor, .. }
+            | Interaction::MessageComponent { interactor, .. } => interactor.user_id(),
+        }
+    }
+}
+
+// Discord sends the invoking user as `member.user` inside a guild, or as a
+// top-level `user` in a DM. Both are optional here since any one interaction
+// carries only one of them.
+#[derive(Debug, Default, Deserialize)]
+struct Interactor {
+    #[serde(default)]
+    member: Option<GuildMember>,
+    #[serde(default)]
+    user: Option<User>,
+}
+
+impl Interactor {
+    fn user_id(&self) -> Option<&str> {
+        self.member
+            .as_ref()
+            .map(|member| member.user.id.as_str())
+            .or_else(|| self.user.as_ref().map(|user| user.id.as_str()))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct GuildMember {
+    user: User,
+}
+
+#[derive(Debug, Deserialize)]
+struct User {
+    id: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageComponentData {
+    custom_id: String,
 }
 
 #[derive(Debug, Deserialize)]
 struct ApplicationCommandData {
     name: String,
+    #[serde(default)]
+    options: Vec<CommandOptionValue>,
+}
+
+impl ApplicationCommandData {
+    fn option_str(&self, name: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .find(|option| option.name == name)?
+            .value
+            .as_str()
+    }
+
+    fn option_u64(&self, name: &str) -> Option<u64> {
+        self.options
+            .iter()
+            .find(|option| option.name == name)?
+            .value
+            .as_u64()
+    }
+
+    fn option_bool(&self, name: &str) -> Option<bool> {
+        self.options
+            .iter()
+            .find(|option| option.name == name)?
+            .value
+            .as_bool()
+    }
+}
+
+#[cfg(test)]
+mod application_command_data_tests {
+    use super::*;
+
+    fn data(options: &str) -> ApplicationCommandData {
+        serde_json::from_str(&format!(r#"{{"name": "city", "options": {options}}}"#)).unwrap()
+    }
+
+    #[test]
+    fn option_str_reads_matching_option() {
+        let data = data(r#"[{"name": "continent", "value": "Europe"}]"#);
+        assert_eq!(data.option_str("continent"), Some("Europe"));
+    }
+
+    #[test]
+    fn option_u64_reads_matching_option() {
+        let data = data(r#"[{"name": "min_population", "value": 100000}]"#);
+        assert_eq!(data.option_u64("min_population"), Some(100_000));
+    }
+
+    #[test]
+    fn option_bool_reads_matching_option() {
+        let data = data(r#"[{"name": "weighted", "value": false}]"#);
+        assert_eq!(data.option_bool("weighted"), Some(false));
+    }
+
+    #[test]
+    fn missing_option_is_none() {
+        let data = data("[]");
+        assert_eq!(data.option_str("continent"), None);
+        assert_eq!(data.option_u64("min_population"), None);
+        assert_eq!(data.option_bool("weighted"), None);
+    }
+
+    #[test]
+    fn wrong_value_type_is_none() {
+        // `min_population` sent as a string rather than an integer.
+        let data = data(r#"[{"name": "min_population", "value": "not a number"}]"#);
+        assert_eq!(data.option_u64("min_population"), None);
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct CommandOptionValue {
+    name: String,
+    value: serde_json::Value,
 }
 
 #[derive(Debug)]
@@ -308,11 +977,67 @@ enum InteractionResponse {
         _type: InteractionCallbackType<4>,
         data: Message,
     },
+    DeferredChannelMessageWithSource {
+        #[serde(rename = "type")]
+        _type: InteractionCallbackType<5>,
+    },
+    UpdateMessage {
+        #[serde(rename = "type")]
+        _type: InteractionCallbackType<7>,
+        data: Message,
+    },
 }
 
 #[derive(Debug, Serialize)]
 struct Message {
     content: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    components: Option<Vec<ActionRow>>,
+}
+
+#[derive(Debug, Serialize)]
+struct ActionRow {
+    #[serde(rename = "type")]
+    _type: ComponentType<1>,
+    components: Vec<Button>,
+}
+
+#[derive(Debug, Serialize)]
+struct Button {
+    #[serde(rename = "type")]
+    _type: ComponentType<2>,
+    style: ButtonStyle,
+    label: &'static str,
+    custom_id: String,
+}
+
+// https://discord.com/developers/docs/interactions/message-components#button-object-button-styles
+#[derive(Debug)]
+enum ButtonStyle {
+    Secondary,
+}
+
+impl Serialize for ButtonStyle {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_u8(match self {
+            ButtonStyle::Secondary => 2,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct ComponentType<const T: u8>;
+
+impl<const T: u8> Serialize for ComponentType<T> {
+    fn serialize<S>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        serializer.serialize_u8(T)
+    }
 }
 
 #[derive(Debug)]
@@ -0,0 +1,138 @@
+// Optional Redis-backed protections so the bot is safe to run as more than
+// one replica: a timestamp-skew check and signature-replay guard run after
+// `verify_discord_message`, plus a per-user sliding-window command quota.
+// All of it is keyed off `REDIS_URL`; if that's unset the server runs
+// exactly as it did before, with no shared state at all.
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bb8::Pool;
+use bb8_redis::{
+    redis::{self, AsyncCommands},
+    RedisConnectionManager,
+};
+use color_eyre::{eyre::eyre, Result};
+
+// Discord recommends treating the timestamp skew check as lenient, since
+// delivery can legitimately lag; default wider than a tight clock-skew bound
+// but still overridable per deployment via `TIMESTAMP_SKEW_SECS`.
+const DEFAULT_MAX_TIMESTAMP_SKEW_SECS: i64 = 30;
+const DEFAULT_QUOTA_PER_WINDOW: usize = 20;
+const QUOTA_WINDOW_SECS: i64 = 60;
+
+#[derive(Clone)]
+pub struct Limiter {
+    pool: Pool<RedisConnectionManager>,
+    max_timestamp_skew_secs: i64,
+    quota_per_window: usize,
+}
+
+impl Limiter {
+    pub async fn connect(redis_url: &str) -> Result<Self> {
+        let manager = RedisConnectionManager::new(redis_url)?;
+        let pool = Pool::builder().build(manager).await?;
+        Ok(Self {
+            pool,
+            max_timestamp_skew_secs: env_or("TIMESTAMP_SKEW_SECS", DEFAULT_MAX_TIMESTAMP_SKEW_SECS),
+            quota_per_window: env_or("QUOTA_PER_WINDOW", DEFAULT_QUOTA_PER_WINDOW),
+        })
+    }
+
+    /// Rejects requests whose `X-Signature-Timestamp` is further from now
+    /// than `TIMESTAMP_SKEW_SECS` (default `DEFAULT_MAX_TIMESTAMP_SKEW_SECS`)
+    /// in either direction.
+    pub fn check_timestamp(&self, timestamp: &str) -> Result<()> {
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs() as i64;
+        check_timestamp_skew(timestamp, now, self.max_timestamp_skew_secs)
+    }
+
+    /// Returns `true` if this exact signature has already been seen within
+    /// the skew window, meaning the request is a replay of an earlier one.
+    ///
+    /// `SET ... NX EX` sets the value and its TTL atomically, so a crash
+    /// between the insert and the expiry can't leave a permanent key behind
+    /// the way a separate `SETNX` + `EXPIRE` pair could. Signatures are only
+    /// worth deduping for as long as a replayed timestamp could still pass
+    /// `check_timestamp`.
+    pub async fn is_replay(&self, signature: &str) -> Result<bool> {
+        let mut conn = self.pool.get().await?;
+        let key = format!("countrygen:seen_signature:{signature}");
+        let signature_ttl_secs = self.max_timestamp_skew_secs.max(0) as u64 * 2;
+        let newly_inserted: bool = redis::cmd("SET")
+            .arg(&key)
+            .arg(true)
+            .arg("NX")
+            .arg("EX")
+            .arg(signature_ttl_secs)
+            .query_async(&mut *conn)
+            .await?;
+        Ok(!newly_inserted)
+    }
+
+    /// Sliding-window quota: each call trims entries older than the window
+    /// from a per-user sorted set, then records `now` in it only if fewer
+    /// than `QUOTA_PER_WINDOW` (default `DEFAULT_QUOTA_PER_WINDOW`) remain.
+    /// Returns `false`, recording nothing, once the user is over quota for
+    /// the current window.
+    pub async fn check_quota(&self, user_id: &str) -> Result<bool> {
+        let mut conn = self.pool.get().await?;
+        let key = format!("countrygen:quota:{user_id}");
+        let now_millis = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis() as i64;
+        let window_start = now_millis - QUOTA_WINDOW_SECS * 1000;
+
+        conn.zrembyscore::<_, _, _, ()>(&key, 0, window_start)
+            .await?;
+        let count: usize = conn.zcard(&key).await?;
+        if count >= self.quota_per_window {
+            return Ok(false);
+        }
+
+        conn.zadd::<_, _, _, ()>(&key, now_millis, now_millis)
+            .await?;
+        conn.expire::<_, ()>(&key, QUOTA_WINDOW_SECS).await?;
+        Ok(true)
+    }
+}
+
+/// Reads `key` from the environment and parses it, falling back to `default`
+/// if it's unset or not a valid value for `T` — lets a deployment tune these
+/// without a recompile, without having to validate every input itself.
+fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    std::env::var(key)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// Pure core of `Limiter::check_timestamp`, split out so the skew boundary
+/// is testable without a live Redis connection.
+fn check_timestamp_skew(timestamp: &str, now: i64, max_skew_secs: i64) -> Result<()> {
+    let timestamp: i64 = timestamp
+        .parse()
+        .map_err(|_| eyre!("timestamp was not an integer"))?;
+    if (now - timestamp).abs() > max_skew_secs {
+        return Err(eyre!("timestamp outside allowed skew"));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_non_integer_timestamp() {
+        assert!(check_timestamp_skew("not-a-timestamp", 1_000, 30).is_err());
+    }
+
+    #[test]
+    fn accepts_timestamp_exactly_at_the_skew_boundary() {
+        assert!(check_timestamp_skew("970", 1_000, 30).is_ok());
+        assert!(check_timestamp_skew("1030", 1_000, 30).is_ok());
+    }
+
+    #[test]
+    fn rejects_timestamp_just_past_the_skew_boundary() {
+        assert!(check_timestamp_skew("969", 1_000, 30).is_err());
+        assert!(check_timestamp_skew("1031", 1_000, 30).is_err());
+    }
+}